@@ -26,6 +26,11 @@ struct Args {
     /// Use only ASCII output characters
     #[arg(short, long)]
     ascii: bool,
+    /// Render each input char in isolation instead of shaping the whole run
+    /// (no kerning/ligatures/RTL, but faster and avoids HarfBuzz-style layout).
+    /// Only this mode uses --width; shaped output is as wide as the shaped text.
+    #[arg(long)]
+    per_char: bool,
 }
 
 const CHUNK_W: u32 = 8;
@@ -36,15 +41,12 @@ fn main() -> Result<(), PicunicError> {
 
     // Load font for rendering input chars
     let font_data = std::fs::read(&args.font)?;
-    let font = Font::from_bytes(font_data, FontSettings::default())
+    let font = Font::from_bytes(font_data.clone(), FontSettings::default())
         .map_err(|e| PicunicError::Model(e.to_string()))?;
 
     // Load embedding matcher
-    let mut matcher = EmbeddingMatcher::new(
-        args.model_dir.join("encoder.onnx"),
-        args.model_dir.join("encoder.embeddings.bin"),
-        args.model_dir.join("encoder.chars.json"),
-    )?;
+    let mut matcher =
+        EmbeddingMatcher::new(args.model_dir.join("encoder.onnx"), args.model_dir.join("encoder.picu"))?;
 
     if args.ascii {
         matcher.filter_ascii();
@@ -53,47 +55,108 @@ fn main() -> Result<(), PicunicError> {
     }
 
     // Dimensions per source char
-    let render_w = CHUNK_W * args.width;
     let render_h = CHUNK_H * args.height;
     let font_size = render_h as f32 * 0.875; // ~87.5% to fit with baseline
 
-    // Render each char and convert to Unicode grid
-    let mut output_rows: Vec<String> = vec![String::new(); args.height as usize];
-
-    for ch in args.text.chars() {
-        let grid = render_char_to_grid(&font, ch, render_w, render_h, font_size, &mut matcher)?;
-        for (i, row) in grid.iter().enumerate() {
-            output_rows[i].push_str(row);
-        }
-    }
+    let grid = if args.per_char {
+        let render_w = CHUNK_W * args.width;
+        render_per_char(&font, &args.text, render_w, render_h, font_size, &mut matcher)?
+    } else {
+        let face = rustybuzz::Face::from_slice(&font_data, 0)
+            .ok_or_else(|| PicunicError::Model("failed to parse font for shaping".into()))?;
+        render_shaped(&face, &font, &args.text, render_h, font_size, &mut matcher)?
+    };
 
-    for row in output_rows {
+    for row in grid {
         println!("{}", row);
     }
 
     Ok(())
 }
 
-fn render_char_to_grid(
+/// Shape the whole `text` as one run (kerning, ligatures, mark attachment, RTL
+/// reordering) and rasterize it into a single canvas before chunking, so the
+/// output reflects real text layout rather than independent per-char glyphs.
+fn render_shaped(
+    face: &rustybuzz::Face,
+    font: &Font,
+    text: &str,
+    height: u32,
+    font_size: f32,
+    matcher: &mut EmbeddingMatcher,
+) -> Result<Vec<String>, PicunicError> {
+    let units_per_em = face.units_per_em() as f32;
+    let scale = font_size / units_per_em;
+
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    let glyph_buffer = rustybuzz::shape(face, &[], buffer);
+
+    let infos = glyph_buffer.glyph_infos();
+    let positions = glyph_buffer.glyph_positions();
+
+    // Lay out pen positions first so we know the total shaped advance.
+    let mut pen_x = 0.0f32;
+    let mut glyphs = Vec::with_capacity(infos.len());
+    for (info, pos) in infos.iter().zip(positions.iter()) {
+        let glyph_id = info.glyph_id as u16;
+        let x = pen_x + pos.x_offset as f32 * scale;
+        let y = pos.y_offset as f32 * scale;
+        glyphs.push((glyph_id, x, y));
+        pen_x += pos.x_advance as f32 * scale;
+    }
+
+    let width = pen_x.ceil().max(1.0) as u32;
+    let mut img = GrayImage::new(width, height);
+    let baseline_y = (height as f32 * 0.75) as i32;
+
+    for (glyph_id, x, y) in glyphs {
+        let (metrics, bitmap) = font.rasterize_indexed(glyph_id, font_size);
+        if metrics.width == 0 || metrics.height == 0 {
+            continue;
+        }
+        let x_offset = x.round() as i32 + metrics.xmin;
+        // y_offset from rustybuzz is positive-up; image ty grows downward, so subtract.
+        let y_offset = baseline_y - metrics.height as i32 - metrics.ymin - y.round() as i32;
+        blit(&mut img, &bitmap, metrics.width, metrics.height, x_offset, y_offset);
+    }
+
+    chunk_and_match(&img, matcher)
+}
+
+/// Render each input char in isolation and concatenate the resulting grids.
+/// Kept as a fallback: no kerning, ligatures, mark attachment, or RTL reordering.
+fn render_per_char(
     font: &Font,
-    ch: char,
+    text: &str,
     width: u32,
     height: u32,
     font_size: f32,
     matcher: &mut EmbeddingMatcher,
 ) -> Result<Vec<String>, PicunicError> {
-    // Render char to grayscale image
-    let img = render_char(font, ch, width, height, font_size);
+    let mut output_rows: Vec<String> = vec![String::new(); (height / CHUNK_H) as usize];
 
-    // Chunk and match
-    let cols = width / CHUNK_W;
-    let rows = height / CHUNK_H;
+    for ch in text.chars() {
+        let img = render_char(font, ch, width, height, font_size);
+        let grid = chunk_and_match(&img, matcher)?;
+        for (i, row) in grid.iter().enumerate() {
+            output_rows[i].push_str(row);
+        }
+    }
+
+    Ok(output_rows)
+}
+
+fn chunk_and_match(img: &GrayImage, matcher: &mut EmbeddingMatcher) -> Result<Vec<String>, PicunicError> {
+    let cols = img.width() / CHUNK_W;
+    let rows = img.height() / CHUNK_H;
 
     let mut grid = Vec::with_capacity(rows as usize);
     for row in 0..rows {
         let mut line = String::with_capacity(cols as usize);
         for col in 0..cols {
-            let chunk = extract_chunk(&img, col * CHUNK_W, row * CHUNK_H, CHUNK_W, CHUNK_H);
+            let chunk = extract_chunk(img, col * CHUNK_W, row * CHUNK_H, CHUNK_W, CHUNK_H);
             let matched = matcher.find_best_match(&chunk)?;
             line.push(matched);
         }
@@ -119,18 +182,24 @@ fn render_char(font: &Font, ch: char, width: u32, height: u32, font_size: f32) -
     // Center horizontally
     let x_offset = (width as i32 - metrics.width as i32) / 2;
 
-    for sy in 0..metrics.height {
-        for sx in 0..metrics.width {
+    blit(&mut img, &bitmap, metrics.width, metrics.height, x_offset, y_offset);
+
+    img
+}
+
+/// Blit a fontdue coverage bitmap onto `img` at (x_offset, y_offset), clipping to bounds.
+fn blit(img: &mut GrayImage, bitmap: &[u8], w: usize, h: usize, x_offset: i32, y_offset: i32) {
+    let (img_w, img_h) = (img.width() as i32, img.height() as i32);
+    for sy in 0..h {
+        for sx in 0..w {
             let tx = x_offset + sx as i32;
             let ty = y_offset + sy as i32;
-            if tx >= 0 && tx < width as i32 && ty >= 0 && ty < height as i32 {
-                let val = bitmap[sy * metrics.width + sx];
+            if tx >= 0 && tx < img_w && ty >= 0 && ty < img_h {
+                let val = bitmap[sy * w + sx];
                 img.put_pixel(tx as u32, ty as u32, Luma([val]));
             }
         }
     }
-
-    img
 }
 
 fn extract_chunk(img: &GrayImage, x: u32, y: u32, w: u32, h: u32) -> Vec<f32> {
@@ -1,22 +1,39 @@
 //! Image chunking - splits image into 8×16 patches for character matching.
 
+use crate::color::{linear_to_srgb, srgb_to_linear};
 use image::GrayImage;
 
 // Terminal cell aspect ratio 1:2
 const CHUNK_W: usize = 8;
 const CHUNK_H: usize = 16;
 
+/// How a chunk's texels are sampled from the source image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleMode {
+    /// Point-sample one source pixel per texel (fast, but aliases badly
+    /// when downscaling a much larger source image).
+    Nearest,
+    /// Box-average every source pixel covered by each texel's footprint.
+    Average,
+}
+
 pub struct ImageChunker {
     image: GrayImage,
     chunk_w: f32,
     chunk_h: f32,
+    sample_mode: SampleMode,
 }
 
 impl ImageChunker {
     pub fn new(image: GrayImage, cols: u32, rows: u32) -> Self {
         let chunk_w = image.width() as f32 / cols as f32;
         let chunk_h = image.height() as f32 / rows as f32;
-        Self { image, chunk_w, chunk_h }
+        Self { image, chunk_w, chunk_h, sample_mode: SampleMode::Average }
+    }
+
+    pub fn with_sample_mode(mut self, mode: SampleMode) -> Self {
+        self.sample_mode = mode;
+        self
     }
 
     /// Extract chunk at (col, row), resized to CHUNK_W × CHUNK_H
@@ -29,6 +46,13 @@ impl ImageChunker {
         let (x1, y1) = (x1.min(self.image.width()), y1.min(self.image.height()));
         let (cw, ch) = ((x1 - x0).max(1), (y1 - y0).max(1));
 
+        match self.sample_mode {
+            SampleMode::Nearest => self.get_chunk_nearest(x0, y0, cw, ch),
+            SampleMode::Average => self.get_chunk_average(x0, y0, cw, ch),
+        }
+    }
+
+    fn get_chunk_nearest(&self, x0: u32, y0: u32, cw: u32, ch: u32) -> Vec<f32> {
         let mut result = vec![0.0; CHUNK_W * CHUNK_H];
         for ty in 0..CHUNK_H {
             for tx in 0..CHUNK_W {
@@ -41,4 +65,36 @@ impl ImageChunker {
         }
         result
     }
+
+    /// Box down-sample: average every source pixel inside each texel's
+    /// footprint `[x0 + tx*cw/CHUNK_W .. x0 + (tx+1)*cw/CHUNK_W) × ...`
+    /// instead of picking a single sample point, accumulating in linear
+    /// light so the average isn't skewed by gamma encoding.
+    fn get_chunk_average(&self, x0: u32, y0: u32, cw: u32, ch: u32) -> Vec<f32> {
+        let (img_w, img_h) = (self.image.width(), self.image.height());
+        let mut result = vec![0.0; CHUNK_W * CHUNK_H];
+
+        for ty in 0..CHUNK_H {
+            let sy0 = y0 + (ty as f32 / CHUNK_H as f32 * ch as f32) as u32;
+            let sy1 = (y0 + ((ty + 1) as f32 / CHUNK_H as f32 * ch as f32).ceil() as u32).min(img_h).max(sy0 + 1);
+            for tx in 0..CHUNK_W {
+                let sx0 = x0 + (tx as f32 / CHUNK_W as f32 * cw as f32) as u32;
+                let sx1 =
+                    (x0 + ((tx + 1) as f32 / CHUNK_W as f32 * cw as f32).ceil() as u32).min(img_w).max(sx0 + 1);
+
+                let mut sum = 0.0f32;
+                let mut count = 0u32;
+                for sy in sy0..sy1.min(img_h) {
+                    for sx in sx0..sx1.min(img_w) {
+                        sum += srgb_to_linear(self.image.get_pixel(sx, sy).0[0]);
+                        count += 1;
+                    }
+                }
+                let avg_linear = sum / count.max(1) as f32;
+                result[ty * CHUNK_W + tx] = linear_to_srgb(avg_linear) as f32 / 255.0;
+            }
+        }
+
+        result
+    }
 }
@@ -0,0 +1,143 @@
+//! Per-chunk average color sampling for the converter's color output modes.
+
+use clap::ValueEnum;
+use image::RgbaImage;
+
+/// How a sampled `Color` is emitted as an ANSI escape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    /// 24-bit foreground/background escapes (`\x1b[38;2;r;g;bm`)
+    Truecolor,
+    /// Nearest of the 256 xterm palette colors (`\x1b[38;5;Nm`)
+    Ansi256,
+}
+
+/// Mean color of the source pixels covered by one output chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    /// ANSI foreground escape for this color in the given mode.
+    pub fn ansi_fg(&self, mode: ColorMode) -> String {
+        match mode {
+            ColorMode::Truecolor => format!("\x1b[38;2;{};{};{}m", self.r, self.g, self.b),
+            ColorMode::Ansi256 => format!("\x1b[38;5;{}m", self.to_ansi256()),
+        }
+    }
+
+    /// ANSI background escape for this color in the given mode.
+    pub fn ansi_bg(&self, mode: ColorMode) -> String {
+        match mode {
+            ColorMode::Truecolor => format!("\x1b[48;2;{};{};{}m", self.r, self.g, self.b),
+            ColorMode::Ansi256 => format!("\x1b[48;5;{}m", self.to_ansi256()),
+        }
+    }
+
+    /// Nearest of the 256 xterm palette colors: the 6×6×6 color cube
+    /// (indices 16-231) or the 24-step grayscale ramp (232-255), whichever
+    /// is closer in RGB distance.
+    fn to_ansi256(&self) -> u8 {
+        // Cube levels are 0, 95, 135, 175, 215, 255: pick the closest one.
+        let to_level = |c: u8| if c < 48 { 0 } else if c < 115 { 1 } else { (c - 35) / 40 };
+        let level_to_channel = |l: u8| if l == 0 { 0 } else { 55 + l * 40 };
+
+        let (lr, lg, lb) = (to_level(self.r), to_level(self.g), to_level(self.b));
+        let cube_index = 16 + 36 * lr + 6 * lg + lb;
+        let cube_channel = |l: u8| level_to_channel(l);
+        let cube_dist = square_dist(
+            self.r, self.g, self.b,
+            cube_channel(lr), cube_channel(lg), cube_channel(lb),
+        );
+
+        let gray_level = ((self.r as u16 + self.g as u16 + self.b as u16) / 3) as u8;
+        let gray_step = ((gray_level.saturating_sub(3)) / 10).min(23);
+        let gray_value = 8 + gray_step * 10;
+        let gray_index = 232 + gray_step;
+        let gray_dist = square_dist(self.r, self.g, self.b, gray_value, gray_value, gray_value);
+
+        if gray_dist < cube_dist { gray_index } else { cube_index }
+    }
+}
+
+fn square_dist(r1: u8, g1: u8, b1: u8, r2: u8, g2: u8, b2: u8) -> i32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+pub const ANSI_RESET: &str = "\x1b[0m";
+
+/// Converts an sRGB channel (0-255) to linear light (0.0-1.0).
+pub(crate) fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Converts a linear-light channel (0.0-1.0) back to sRGB (0-255).
+pub(crate) fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let s = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (s * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Samples the mean RGBA color under the same col/row block geometry
+/// `ImageChunker` uses for glyph matching, so colors line up with glyphs.
+/// Averaging happens in linear light rather than gamma space, matching the
+/// perceptually correct averaging used elsewhere for downscaling.
+pub struct ColorSampler {
+    image: RgbaImage,
+    chunk_w: f32,
+    chunk_h: f32,
+}
+
+impl ColorSampler {
+    pub fn new(image: RgbaImage, cols: u32, rows: u32) -> Self {
+        let chunk_w = image.width() as f32 / cols as f32;
+        let chunk_h = image.height() as f32 / rows as f32;
+        Self { image, chunk_w, chunk_h }
+    }
+
+    /// Average color of the source region covered by chunk (col, row).
+    pub fn get_color(&self, col: u32, row: u32) -> Color {
+        self.get_color_band(col, row, 1, 0)
+    }
+
+    /// Average color of one vertical band of chunk (col, row): with
+    /// `bands = 2`, `band = 0` is the top half and `band = 1` the bottom
+    /// half, used by half-block mode to sample two colors per cell.
+    pub fn get_color_band(&self, col: u32, row: u32, bands: u32, band: u32) -> Color {
+        let (img_w, img_h) = (self.image.width(), self.image.height());
+        let x0 = (col as f32 * self.chunk_w) as u32;
+        let x1 = (((col + 1) as f32 * self.chunk_w).ceil() as u32).min(img_w).max(x0 + 1);
+
+        let band_h = self.chunk_h / bands as f32;
+        let y0 = (row as f32 * self.chunk_h + band as f32 * band_h) as u32;
+        let y1 = ((row as f32 * self.chunk_h + (band + 1) as f32 * band_h).ceil() as u32)
+            .min(img_h)
+            .max(y0 + 1);
+
+        let (mut r, mut g, mut b, mut a) = (0.0f32, 0.0f32, 0.0f32, 0u64);
+        let mut count = 0u64;
+        for y in y0..y1.min(img_h) {
+            for x in x0..x1.min(img_w) {
+                let px = self.image.get_pixel(x, y).0;
+                r += srgb_to_linear(px[0]);
+                g += srgb_to_linear(px[1]);
+                b += srgb_to_linear(px[2]);
+                a += px[3] as u64;
+                count += 1;
+            }
+        }
+        let count = count.max(1);
+        Color {
+            r: linear_to_srgb(r / count as f32),
+            g: linear_to_srgb(g / count as f32),
+            b: linear_to_srgb(b / count as f32),
+            a: (a / count) as u8,
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! Packed, versioned, CRC-checked container (`.picu`) for the character
+//! table, per-char luminosities and embedding matrix. Replaces the old
+//! loose asset pair (`encoder.chars.json` holding `chars`, `embedding_dim`
+//! and `luminosities`, plus a flat binary `encoder.embeddings.bin`) with
+//! one file whose integrity can be verified before use, which matters most
+//! for the WASM build fetching assets over the network. `read_legacy`
+//! reads that old JSON+binary layout directly, for asset directories not
+//! yet repacked; `EmbeddingMatcher::new` falls back to it automatically
+//! when no `.picu` file is found.
+//!
+//! Kept little-endian with a `u32` version field (rather than a big-endian
+//! `u16` version) to match the little-endian byte order `read_legacy`
+//! already reads and the `u32` width of the other header fields - not
+//! worth a mixed-endianness format for one field.
+//!
+//! Layout (all integers little-endian):
+//! `magic: [u8; 4] = b"PICU"`, `version: u32`, `embedding_dim: u32`,
+//! `char_count: u32`, `quantized: u32` (0 or 1), `scale: f32` (quantization
+//! scale, unused when not quantized), `chars: [u32; char_count]` (UTF-32
+//! code points), `luminosities: [f32; char_count]`, `embeddings` (either
+//! `[f32; char_count * embedding_dim]` or, when quantized,
+//! `[i8; char_count * embedding_dim]`), `crc32: u32` (CRC-32/ISO-HDLC over
+//! everything before it).
+
+use crate::{PicunicError, Result};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"PICU";
+const FORMAT_VERSION: u32 = 2;
+
+/// The embedding matrix, either full-precision or int8-quantized with a
+/// single global scale (embeddings are L2-normalized, so one scale per
+/// table is enough: `s = 127 / max_abs_component`).
+pub enum Embeddings {
+    F32(Vec<f32>),
+    I8 { data: Vec<i8>, scale: f32 },
+}
+
+/// Character table, luminosities and embedding matrix loaded from a `.picu` file.
+pub struct ModelData {
+    pub chars: Vec<char>,
+    pub luminosities: Vec<f32>,
+    pub embeddings: Embeddings,
+    pub embedding_dim: usize,
+}
+
+/// Quantize an L2-normalized embedding matrix to `i8` with a single global
+/// scale `s = 127 / max_abs_component`.
+pub fn quantize(embeddings: &[f32]) -> (Vec<i8>, f32) {
+    let max_abs = embeddings.iter().fold(0.0f32, |m, &v| m.max(v.abs()));
+    let scale = if max_abs > 0.0 { 127.0 / max_abs } else { 1.0 };
+    let data = embeddings.iter().map(|&v| (v * scale).round().clamp(-127.0, 127.0) as i8).collect();
+    (data, scale)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| PicunicError::Model("not enough data".into()))?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// Bytes left to read. Used to cap `Vec::with_capacity` against an
+    /// untrusted header count before any of those bytes are actually read.
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        let b = self.take(4)?;
+        Ok(f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// Parse and validate a `.picu` container, checking the CRC-32 before
+/// trusting any of the payload.
+pub fn read_picu(bytes: &[u8]) -> Result<ModelData> {
+    let payload_len = bytes
+        .len()
+        .checked_sub(4)
+        .ok_or_else(|| PicunicError::Model("not enough data".into()))?;
+    let (payload, crc_bytes) = bytes.split_at(payload_len);
+    let expected_crc = u32::from_le_bytes([crc_bytes[0], crc_bytes[1], crc_bytes[2], crc_bytes[3]]);
+    let actual_crc = crc32(payload);
+    if actual_crc != expected_crc {
+        return Err(PicunicError::Model(format!(
+            "corrupt .picu file: CRC mismatch (expected {expected_crc:#010x}, got {actual_crc:#010x})"
+        )));
+    }
+
+    let mut r = Reader::new(payload);
+    if r.take(4)? != MAGIC {
+        return Err(PicunicError::Model("not a .picu file (bad magic)".into()));
+    }
+    let version = r.u32()?;
+    if version != FORMAT_VERSION {
+        return Err(PicunicError::Model(format!("unsupported .picu format version {version}")));
+    }
+    let embedding_dim = r.u32()? as usize;
+    let char_count = r.u32()? as usize;
+    let quantized = r.u32()? != 0;
+    let scale = r.f32()?;
+
+    // `char_count`/`embedding_dim` come straight from the header and are
+    // untrusted; cap every `with_capacity` at what's actually left in the
+    // buffer so a crafted header can't force a multi-GB allocation ahead of
+    // the "not enough data" error `take`/`u32`/`f32` would otherwise return.
+    let mut chars = Vec::with_capacity(char_count.min(r.remaining() / 4));
+    for _ in 0..char_count {
+        let cp = r.u32()?;
+        chars.push(
+            char::from_u32(cp).ok_or_else(|| PicunicError::Model(format!("invalid code point {cp:#x}")))?,
+        );
+    }
+
+    let mut luminosities = Vec::with_capacity(char_count.min(r.remaining() / 4));
+    for _ in 0..char_count {
+        luminosities.push(r.f32()?);
+    }
+
+    let count = char_count
+        .checked_mul(embedding_dim)
+        .ok_or_else(|| PicunicError::Model("char_count * embedding_dim overflows usize".into()))?;
+    let embeddings = if quantized {
+        let mut data = Vec::with_capacity(count.min(r.remaining()));
+        for _ in 0..count {
+            data.push(r.take(1)?[0] as i8);
+        }
+        Embeddings::I8 { data, scale }
+    } else {
+        let mut data = Vec::with_capacity(count.min(r.remaining() / 4));
+        for _ in 0..count {
+            data.push(r.f32()?);
+        }
+        Embeddings::F32(data)
+    };
+
+    Ok(ModelData { chars, luminosities, embeddings, embedding_dim })
+}
+
+/// Pack a character table, luminosities and embedding matrix into a `.picu` file.
+pub fn write_picu(data: &ModelData) -> Vec<u8> {
+    let mut payload = Vec::new();
+    payload.extend_from_slice(MAGIC);
+    payload.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    payload.extend_from_slice(&(data.embedding_dim as u32).to_le_bytes());
+    payload.extend_from_slice(&(data.chars.len() as u32).to_le_bytes());
+    match &data.embeddings {
+        Embeddings::F32(_) => {
+            payload.extend_from_slice(&0u32.to_le_bytes());
+            payload.extend_from_slice(&1.0f32.to_le_bytes());
+        }
+        Embeddings::I8 { scale, .. } => {
+            payload.extend_from_slice(&1u32.to_le_bytes());
+            payload.extend_from_slice(&scale.to_le_bytes());
+        }
+    }
+    for &c in &data.chars {
+        payload.extend_from_slice(&(c as u32).to_le_bytes());
+    }
+    for &l in &data.luminosities {
+        payload.extend_from_slice(&l.to_le_bytes());
+    }
+    match &data.embeddings {
+        Embeddings::F32(v) => {
+            for &e in v {
+                payload.extend_from_slice(&e.to_le_bytes());
+            }
+        }
+        Embeddings::I8 { data, .. } => {
+            for &b in data {
+                payload.push(b as u8);
+            }
+        }
+    }
+
+    let crc = crc32(&payload);
+    payload.extend_from_slice(&crc.to_le_bytes());
+    payload
+}
+
+/// Load the pre-`.picu` loose-asset layout: a `chars.json` file holding
+/// `chars` (array of one-char strings), `embedding_dim`, and an optional
+/// `luminosities` array (defaulting to 0.5 per char for files predating
+/// that field), plus a flat little-endian `f32` `embeddings.bin` with no
+/// header or checksum. Kept as a fallback for asset directories that
+/// haven't been repacked into a `.picu` file yet.
+pub fn read_legacy(chars_json_path: impl AsRef<Path>, embeddings_path: impl AsRef<Path>) -> Result<ModelData> {
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(chars_json_path)?)
+        .map_err(|e| PicunicError::Model(e.to_string()))?;
+    let chars: Vec<char> = json["chars"]
+        .as_array()
+        .ok_or_else(|| PicunicError::Model("legacy chars file missing \"chars\" array".into()))?
+        .iter()
+        .filter_map(|v| v.as_str()?.chars().next())
+        .collect();
+    let embedding_dim = json["embedding_dim"]
+        .as_u64()
+        .ok_or_else(|| PicunicError::Model("legacy chars file missing \"embedding_dim\"".into()))?
+        as usize;
+    let luminosities: Vec<f32> = json["luminosities"]
+        .as_array()
+        .map(|arr| arr.iter().filter_map(|v| v.as_f64().map(|f| f as f32)).collect())
+        .unwrap_or_else(|| vec![0.5; chars.len()]);
+
+    if luminosities.len() != chars.len() {
+        return Err(PicunicError::Model(format!(
+            "legacy luminosities count ({}) does not match chars count ({})",
+            luminosities.len(),
+            chars.len()
+        )));
+    }
+
+    let embeddings = read_f32_vec(embeddings_path)?;
+    if embeddings.len() != chars.len() * embedding_dim {
+        return Err(PicunicError::Model(format!(
+            "legacy embeddings length ({}) does not match chars ({}) * embedding_dim ({})",
+            embeddings.len(),
+            chars.len(),
+            embedding_dim
+        )));
+    }
+
+    Ok(ModelData { chars, luminosities, embeddings: Embeddings::F32(embeddings), embedding_dim })
+}
+
+fn read_f32_vec(path: impl AsRef<Path>) -> Result<Vec<f32>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() % 4 != 0 {
+        return Err(PicunicError::Model("legacy binary file length is not a multiple of 4".into()));
+    }
+    Ok(bytes.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect())
+}
+
+const CRC32_TABLE: [u32; 256] = build_crc32_table();
+
+const fn build_crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32/ISO-HDLC: reflected polynomial 0xEDB88320, init 0xFFFFFFFF, final XOR 0xFFFFFFFF.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for &byte in data {
+        let idx = ((crc ^ byte as u32) & 0xFF) as usize;
+        crc = CRC32_TABLE[idx] ^ (crc >> 8);
+    }
+    crc ^ 0xFFFFFFFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ModelData {
+        ModelData {
+            chars: vec!['a', 'b', 'c'],
+            luminosities: vec![0.1, 0.5, 0.9],
+            embeddings: Embeddings::F32(vec![
+                1.0, 0.0, 0.0, 0.0,
+                0.0, 1.0, 0.0, 0.0,
+                0.0, 0.0, 1.0, 0.0,
+            ]),
+            embedding_dim: 4,
+        }
+    }
+
+    #[test]
+    fn round_trips_through_write_and_read() {
+        let bytes = write_picu(&sample());
+        let model = read_picu(&bytes).expect("valid container should parse");
+
+        assert_eq!(model.chars, sample().chars);
+        assert_eq!(model.luminosities, sample().luminosities);
+        assert_eq!(model.embedding_dim, 4);
+        match model.embeddings {
+            Embeddings::F32(v) => assert_eq!(v, vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0]),
+            Embeddings::I8 { .. } => panic!("expected F32 embeddings"),
+        }
+    }
+
+    #[test]
+    fn round_trips_quantized_embeddings() {
+        let (data, scale) = quantize(&[1.0, 0.0, 0.0, 0.0, -0.5, 0.5, 0.5, -0.5, 0.0, 0.0, 1.0, 0.0]);
+        let mut model = sample();
+        model.embeddings = Embeddings::I8 { data, scale };
+
+        let bytes = write_picu(&model);
+        let parsed = read_picu(&bytes).expect("valid quantized container should parse");
+        match parsed.embeddings {
+            Embeddings::I8 { data, scale: parsed_scale } => {
+                assert_eq!(data, vec![127, 0, 0, 0, -64, 64, 64, -64, 0, 0, 127, 0]);
+                assert_eq!(parsed_scale, scale);
+            }
+            Embeddings::F32(_) => panic!("expected I8 embeddings"),
+        }
+    }
+
+    #[test]
+    fn rejects_corrupted_crc() {
+        let mut bytes = write_picu(&sample());
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+
+        let err = read_picu(&bytes).unwrap_err().to_string();
+        assert!(err.contains("CRC mismatch"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let bytes = write_picu(&sample());
+        let truncated = &bytes[..bytes.len() / 2];
+
+        assert!(read_picu(truncated).is_err());
+    }
+
+    #[test]
+    fn quantize_maps_max_abs_component_to_127() {
+        let (data, scale) = quantize(&[0.5, -2.0, 1.0]);
+        assert_eq!(scale, 127.0 / 2.0);
+        assert_eq!(data, vec![32, -127, 64]);
+    }
+
+    #[test]
+    fn rejects_oversized_header_count_without_huge_allocation() {
+        // A header claiming far more chars than the file actually holds
+        // (but with a correct CRC over that short payload) must error out
+        // via `take`/`u32`, not pre-allocate gigabytes of capacity.
+        let mut payload = Vec::new();
+        payload.extend_from_slice(MAGIC);
+        payload.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        payload.extend_from_slice(&4u32.to_le_bytes()); // embedding_dim
+        payload.extend_from_slice(&u32::MAX.to_le_bytes()); // char_count: wildly oversized
+        payload.extend_from_slice(&0u32.to_le_bytes()); // quantized
+        payload.extend_from_slice(&1.0f32.to_le_bytes()); // scale
+        let crc = crc32(&payload);
+        payload.extend_from_slice(&crc.to_le_bytes());
+
+        let err = read_picu(&payload).unwrap_err().to_string();
+        assert!(err.contains("not enough data"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn read_legacy_round_trips_real_layout() {
+        let dir = std::env::temp_dir().join(format!("picu-test-{:x}", crc32(b"legacy-round-trip")));
+        std::fs::create_dir_all(&dir).unwrap();
+        let chars_json_path = dir.join("encoder.chars.json");
+        let embeddings_path = dir.join("encoder.embeddings.bin");
+
+        std::fs::write(
+            &chars_json_path,
+            r#"{"chars": ["a", "b"], "embedding_dim": 2, "luminosities": [0.25, 0.75]}"#,
+        )
+        .unwrap();
+        let mut embeddings_bytes = Vec::new();
+        for f in [1.0f32, 0.0, 0.0, 1.0] {
+            embeddings_bytes.extend_from_slice(&f.to_le_bytes());
+        }
+        std::fs::write(&embeddings_path, &embeddings_bytes).unwrap();
+
+        let model = read_legacy(&chars_json_path, &embeddings_path).expect("valid legacy layout should parse");
+        assert_eq!(model.chars, vec!['a', 'b']);
+        assert_eq!(model.luminosities, vec![0.25, 0.75]);
+        assert_eq!(model.embedding_dim, 2);
+        match model.embeddings {
+            Embeddings::F32(v) => assert_eq!(v, vec![1.0, 0.0, 0.0, 1.0]),
+            Embeddings::I8 { .. } => panic!("expected F32 embeddings"),
+        }
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn read_legacy_defaults_luminosities_when_absent() {
+        let dir = std::env::temp_dir().join(format!("picu-test-{:x}", crc32(b"legacy-default-lum")));
+        std::fs::create_dir_all(&dir).unwrap();
+        let chars_json_path = dir.join("encoder.chars.json");
+        let embeddings_path = dir.join("encoder.embeddings.bin");
+
+        std::fs::write(&chars_json_path, r#"{"chars": ["a"], "embedding_dim": 1}"#).unwrap();
+        std::fs::write(&embeddings_path, 1.0f32.to_le_bytes()).unwrap();
+
+        let model = read_legacy(&chars_json_path, &embeddings_path).expect("valid legacy layout should parse");
+        assert_eq!(model.luminosities, vec![0.5]);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn read_legacy_rejects_mismatched_embeddings_length() {
+        let dir = std::env::temp_dir().join(format!("picu-test-{:x}", crc32(b"mismatched-emb")));
+        std::fs::create_dir_all(&dir).unwrap();
+        let chars_json_path = dir.join("encoder.chars.json");
+        let embeddings_path = dir.join("encoder.embeddings.bin");
+
+        std::fs::write(&chars_json_path, r#"{"chars": ["a", "b"], "embedding_dim": 2}"#).unwrap();
+        std::fs::write(&embeddings_path, [0u8; 4]).unwrap(); // only 1 float, need 2 chars * 2 dim = 4
+
+        let err = read_legacy(&chars_json_path, &embeddings_path).unwrap_err().to_string();
+        assert!(err.contains("does not match"), "unexpected error: {err}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn read_legacy_rejects_truncated_embeddings_file() {
+        let dir = std::env::temp_dir().join(format!("picu-test-{:x}", crc32(b"truncated-emb")));
+        std::fs::create_dir_all(&dir).unwrap();
+        let chars_json_path = dir.join("encoder.chars.json");
+        let embeddings_path = dir.join("encoder.embeddings.bin");
+
+        std::fs::write(&chars_json_path, r#"{"chars": ["a"], "embedding_dim": 1}"#).unwrap();
+        std::fs::write(&embeddings_path, [0u8; 3]).unwrap(); // not a multiple of 4
+
+        let err = read_legacy(&chars_json_path, &embeddings_path).unwrap_err().to_string();
+        assert!(err.contains("multiple of 4"), "unexpected error: {err}");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
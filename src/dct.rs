@@ -0,0 +1,101 @@
+//! DCT-based glyph matcher: a model-free alternative to `EmbeddingMatcher`
+//! that needs only a font and a `chars` list, no `.onnx`/`.picu` model files.
+
+use crate::glyph::{GlyphRenderer, CHUNK_H, CHUNK_W};
+use crate::{Matcher, Result};
+use std::f32::consts::PI;
+use std::path::Path;
+
+const NX: usize = 4;
+const NY: usize = 4;
+
+/// Matches chunks by the cosine distance between low-frequency DCT
+/// descriptors instead of CNN embeddings.
+pub struct DctMatcher {
+    chars: Vec<char>,
+    descriptors: Vec<Vec<f32>>,
+}
+
+impl DctMatcher {
+    /// Render every candidate char with `font` into an 8×16 grayscale
+    /// bitmap and precompute its descriptor once at load time.
+    pub fn new(font_path: impl AsRef<Path>, chars: Vec<char>) -> Result<Self> {
+        let renderer = GlyphRenderer::new(font_path)?;
+        let descriptors = chars.iter().map(|&c| descriptor(&renderer.render(c))).collect();
+        Ok(Self { chars, descriptors })
+    }
+
+    /// Restrict matching to ASCII output characters (0x20-0x7E).
+    pub fn filter_ascii(&mut self) {
+        let mut chars = Vec::new();
+        let mut descriptors = Vec::new();
+        for (c, d) in self.chars.iter().zip(self.descriptors.iter()) {
+            if (*c as u32) >= 0x20 && (*c as u32) <= 0x7E {
+                chars.push(*c);
+                descriptors.push(d.clone());
+            }
+        }
+        self.chars = chars;
+        self.descriptors = descriptors;
+    }
+
+    /// Glyph whose descriptor has the smallest cosine distance to `chunk`'s.
+    pub fn find_best_match(&mut self, chunk: &[f32]) -> Result<char> {
+        let query = descriptor(chunk);
+        let mut best_idx = 0;
+        let mut best_dist = f32::INFINITY;
+        for (i, d) in self.descriptors.iter().enumerate() {
+            let dist = 1.0 - dot(&query, d);
+            if dist < best_dist {
+                best_dist = dist;
+                best_idx = i;
+            }
+        }
+        Ok(self.chars[best_idx])
+    }
+}
+
+impl Matcher for DctMatcher {
+    fn find_best_match(&mut self, chunk: &[f32]) -> Result<char> {
+        self.find_best_match(chunk)
+    }
+
+    fn filter_ascii(&mut self) {
+        self.filter_ascii()
+    }
+}
+
+/// Low-frequency DCT-II descriptor over `i in 0..NX, j in 0..NY`, dropping
+/// the DC term (i = j = 0) so brightness bias doesn't dominate, then
+/// L2-normalized.
+fn descriptor(chunk: &[f32]) -> Vec<f32> {
+    let mut coeffs = Vec::with_capacity(NX * NY - 1);
+    for j in 0..NY {
+        for i in 0..NX {
+            if i == 0 && j == 0 {
+                continue;
+            }
+            let mut sum = 0.0f32;
+            for y in 0..CHUNK_H {
+                for x in 0..CHUNK_W {
+                    sum += chunk[y * CHUNK_W + x]
+                        * (PI * i as f32 * (x as f32 + 0.5) / CHUNK_W as f32).cos()
+                        * (PI * j as f32 * (y as f32 + 0.5) / CHUNK_H as f32).cos();
+                }
+            }
+            coeffs.push(sum);
+        }
+    }
+
+    let norm = coeffs.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for c in &mut coeffs {
+            *c /= norm;
+        }
+    }
+    coeffs
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
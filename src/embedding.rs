@@ -1,25 +1,111 @@
 //! CNN embedding-based character matcher using ONNX runtime.
 
+use crate::container::{self, Embeddings};
 use crate::{PicunicError, Result};
-use ndarray::{Array2, ArrayView1};
+use ndarray::{Array2, ArrayView2};
 use ort::session::{builder::GraphOptimizationLevel, Session};
 use ort::value::Tensor;
 use std::path::Path;
 
+/// The char embedding table, either full-precision or int8-quantized.
+/// Quantized tables store one byte per component instead of four, a quarter
+/// of the on-the-wire size of the float matrix, and let matching
+/// run as an integer dot product instead of a float one.
+enum EmbeddingTable {
+    F32(Array2<f32>),
+    I8 { data: Array2<i8>, scale: f32 },
+}
+
+impl EmbeddingTable {
+    fn ncols(&self) -> usize {
+        match self {
+            EmbeddingTable::F32(a) => a.ncols(),
+            EmbeddingTable::I8 { data, .. } => data.ncols(),
+        }
+    }
+
+    fn select_rows(&self, indices: &[usize]) -> Self {
+        let dim = self.ncols();
+        match self {
+            EmbeddingTable::F32(a) => {
+                let rows: Vec<f32> = indices.iter().flat_map(|&i| a.row(i).to_vec()).collect();
+                EmbeddingTable::F32(Array2::from_shape_vec((indices.len(), dim), rows).expect("shape mismatch"))
+            }
+            EmbeddingTable::I8 { data, scale } => {
+                let rows: Vec<i8> = indices.iter().flat_map(|&i| data.row(i).to_vec()).collect();
+                EmbeddingTable::I8 {
+                    data: Array2::from_shape_vec((indices.len(), dim), rows).expect("shape mismatch"),
+                    scale: *scale,
+                }
+            }
+        }
+    }
+
+    /// Cosine similarity of every row (C chars) against every row of
+    /// `queries` (N embeddings), normalized to [0, 1], as a C×N matrix.
+    /// For a float table this is one GEMM (`char_embeddings · queries^T`);
+    /// a quantized table falls back to per-query integer dot products.
+    fn normalized_similarity_matrix(&self, queries: ArrayView2<f32>) -> Array2<f32> {
+        match self {
+            EmbeddingTable::F32(table) => table.dot(&queries.t()).mapv(|sim| (sim + 1.0) * 0.5),
+            EmbeddingTable::I8 { data, scale } => {
+                let (chars, n) = (data.nrows(), queries.nrows());
+                let mut sims = Array2::zeros((chars, n));
+                for (col, query) in queries.rows().into_iter().enumerate() {
+                    let (query, query_scale) = container::quantize(query.as_slice().expect("contiguous"));
+                    for (row, char_emb) in data.rows().into_iter().enumerate() {
+                        let dot: i32 = char_emb.iter().zip(query.iter()).map(|(&a, &b)| a as i32 * b as i32).sum();
+                        sims[[row, col]] = (dot as f32 / (scale * query_scale) + 1.0) * 0.5;
+                    }
+                }
+                sims
+            }
+        }
+    }
+}
+
 pub struct EmbeddingMatcher {
     session: Session,
-    char_embeddings: Array2<f32>,
+    char_embeddings: EmbeddingTable,
     chars: Vec<char>,
     char_luminosities: Vec<f32>,  // Precomputed average luminosities (0-1 range)
     edge_weight: f32,  // Weight for edge similarity (0-1), luminosity weight is (1 - edge_weight)
 }
 
 impl EmbeddingMatcher {
-    pub fn new(
+    pub fn new(model_path: impl AsRef<Path>, picu_path: impl AsRef<Path>) -> Result<Self> {
+        // Load the char table, luminosities and embedding matrix from the
+        // packed, CRC-checked container in one shot. Whether matching runs
+        // in float or quantized int8 is decided by how the container was
+        // written, not by a runtime flag. If the directory hasn't been
+        // repacked yet, fall back to the pre-`.picu` `<stem>.chars.json` +
+        // `<stem>.embeddings.bin` pair next to it.
+        match std::fs::read(picu_path.as_ref()) {
+            Ok(bytes) => Self::from_model(model_path, container::read_picu(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let dir = picu_path.as_ref().parent().unwrap_or_else(|| Path::new("."));
+                let stem = picu_path.as_ref().file_stem().and_then(|s| s.to_str()).unwrap_or("encoder");
+                Self::new_legacy(model_path, dir.join(format!("{stem}.chars.json")), dir.join(format!("{stem}.embeddings.bin")))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Fallback for asset directories still using the pre-`.picu` loose
+    /// assets: a `<stem>.chars.json` file (`chars`, `embedding_dim`,
+    /// optional `luminosities`) plus a flat `<stem>.embeddings.bin`. `new`
+    /// calls this automatically when no `.picu` file is found next to
+    /// `picu_path`.
+    pub fn new_legacy(
         model_path: impl AsRef<Path>,
+        chars_json_path: impl AsRef<Path>,
         embeddings_path: impl AsRef<Path>,
-        chars_path: impl AsRef<Path>,
     ) -> Result<Self> {
+        let model = container::read_legacy(chars_json_path, embeddings_path)?;
+        Self::from_model(model_path, model)
+    }
+
+    fn from_model(model_path: impl AsRef<Path>, model: container::ModelData) -> Result<Self> {
         let session = Session::builder()
             .map_err(|e| PicunicError::Model(e.to_string()))?
             .with_optimization_level(GraphOptimizationLevel::Level3)
@@ -27,46 +113,23 @@ impl EmbeddingMatcher {
             .commit_from_file(model_path.as_ref())
             .map_err(|e| PicunicError::Model(e.to_string()))?;
 
-        // Load character list, embedding dimension, and luminosities
-        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(chars_path)?)
-            .map_err(|e| PicunicError::Model(e.to_string()))?;
-        let chars: Vec<char> = json["chars"]
-            .as_array()
-            .unwrap()
-            .iter()
-            .filter_map(|v| v.as_str()?.chars().next())
-            .collect();
-        let dim = json["embedding_dim"].as_u64().unwrap() as usize;
-        
-        // Load luminosities (fallback to 0.5 if not present for backward compatibility)
-        let char_luminosities: Vec<f32> = json["luminosities"]
-            .as_array()
-            .map(|arr| arr.iter().filter_map(|v| v.as_f64().map(|f| f as f32)).collect())
-            .unwrap_or_else(|| vec![0.5; chars.len()]);
-
-        // Load precomputed embeddings
-        let bytes = std::fs::read(embeddings_path)?;
-        let floats: Vec<f32> = bytes
-            .chunks_exact(4)
-            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-            .collect();
-        let char_embeddings = Array2::from_shape_vec((chars.len(), dim), floats)
-            .map_err(|e| PicunicError::Model(e.to_string()))?;
-
-        if char_luminosities.len() != chars.len() {
-            return Err(PicunicError::Model(format!(
-                "Luminosity count {} doesn't match char count {}",
-                char_luminosities.len(),
-                chars.len()
-            )));
-        }
-
-        Ok(Self { 
-            session, 
-            char_embeddings, 
-            chars,
-            char_luminosities,
-            edge_weight: 1.0,  // Default: pure edge matching
+        let (rows, dim) = (model.chars.len(), model.embedding_dim);
+        let char_embeddings = match model.embeddings {
+            Embeddings::F32(v) => EmbeddingTable::F32(
+                Array2::from_shape_vec((rows, dim), v).map_err(|e| PicunicError::Model(e.to_string()))?,
+            ),
+            Embeddings::I8 { data, scale } => EmbeddingTable::I8 {
+                data: Array2::from_shape_vec((rows, dim), data).map_err(|e| PicunicError::Model(e.to_string()))?,
+                scale,
+            },
+        };
+
+        Ok(Self {
+            session,
+            char_embeddings,
+            chars: model.chars,
+            char_luminosities: model.luminosities,
+            edge_weight: 1.0, // Default: pure edge matching
         })
     }
 
@@ -92,22 +155,16 @@ impl EmbeddingMatcher {
     fn filter(&mut self, predicate: impl Fn(char) -> bool) {
         let mut indices = Vec::new();
         let mut new_chars = Vec::new();
-        
+
         for (i, &c) in self.chars.iter().enumerate() {
             if predicate(c) {
                 indices.push(i);
                 new_chars.push(c);
             }
         }
-        
-        let dim = self.char_embeddings.ncols();
-        let new_embeddings: Vec<f32> = indices.iter()
-            .flat_map(|&i| self.char_embeddings.row(i).to_vec())
-            .collect();
-        
-        self.char_embeddings = Array2::from_shape_vec((new_chars.len(), dim), new_embeddings)
-            .expect("shape mismatch");
-        
+
+        self.char_embeddings = self.char_embeddings.select_rows(&indices);
+
         // Filter luminosities too
         let new_luminosities: Vec<f32> = indices.iter()
             .map(|&i| self.char_luminosities[i])
@@ -124,47 +181,55 @@ impl EmbeddingMatcher {
         self.edge_weight = weight.clamp(0.0, 1.0);
     }
 
+    /// Thin wrapper over `find_best_matches` for callers (like `bigfoont`)
+    /// that only ever have one chunk at a time.
     pub fn find_best_match(&mut self, chunk: &[f32]) -> Result<char> {
-        // Compute chunk average luminosity (0-1 range)
         let chunk_lum: f32 = chunk.iter().sum::<f32>() / chunk.len() as f32;
+        Ok(self.find_best_matches(std::slice::from_ref(&chunk.to_vec()), &[chunk_lum])?[0])
+    }
 
-        // Input shape: (batch=1, channels=1, H=16, W=8)
-        let input = Tensor::from_array(([1usize, 1, 16, 8], chunk.to_vec()))
-            .map_err(|e| PicunicError::Model(e.to_string()))?;
+    /// Match a whole image's worth of chunks in one batched ONNX inference
+    /// call and one similarity GEMM, instead of one tiny `session.run` and
+    /// dot product per chunk.
+    pub fn find_best_matches(&mut self, chunks: &[Vec<f32>], chunk_lums: &[f32]) -> Result<Vec<char>> {
+        if chunks.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let outputs = self.session.run(ort::inputs![input])
-            .map_err(|e| PicunicError::Model(e.to_string()))?;
+        let n = chunks.len();
+        let flat: Vec<f32> = chunks.iter().flat_map(|c| c.iter().copied()).collect();
 
-        let emb = outputs[0].try_extract_tensor::<f32>()
-            .map_err(|e| PicunicError::Model(e.to_string()))?;
+        // Input shape: (batch=N, channels=1, H=16, W=8)
+        let input = Tensor::from_array(([n, 1, 16, 8], flat)).map_err(|e| PicunicError::Model(e.to_string()))?;
 
-        // Cosine similarity (embeddings are normalized, range [-1, 1])
-        let emb_view = ArrayView1::from(emb.1);
-        let edge_sims = self.char_embeddings.dot(&emb_view);
+        let outputs = self.session.run(ort::inputs![input]).map_err(|e| PicunicError::Model(e.to_string()))?;
 
-        // Normalize edge similarity to [0, 1] range
-        let normalized_edge_sims: Vec<f32> = edge_sims.iter()
-            .map(|&sim| (sim + 1.0) * 0.5)
-            .collect();
+        let emb = outputs[0].try_extract_tensor::<f32>().map_err(|e| PicunicError::Model(e.to_string()))?;
+        let dim = self.char_embeddings.ncols();
+        let embeddings = ArrayView2::from_shape((n, dim), emb.1).map_err(|e| PicunicError::Model(e.to_string()))?;
 
-        // Compute combined scores: w * edge_sim + (1-w) * lum_sim
-        let mut best_idx = 0;
-        let mut best_score = f32::NEG_INFINITY;
+        // One C×N similarity matrix for the whole batch instead of N dot products.
+        let sims = self.char_embeddings.normalized_similarity_matrix(embeddings);
 
-        for (i, &edge_sim) in normalized_edge_sims.iter().enumerate() {
-            // Luminosity similarity: 1 - normalized absolute difference
-            let lum_diff = (chunk_lum - self.char_luminosities[i]).abs();
-            let lum_sim = 1.0 - lum_diff;  // Range [0, 1], higher = more similar
+        let mut results = Vec::with_capacity(n);
+        for col in 0..n {
+            let mut best_idx = 0;
+            let mut best_score = f32::NEG_INFINITY;
 
-            // Combined score
-            let score = self.edge_weight * edge_sim + (1.0 - self.edge_weight) * lum_sim;
+            for (row, &lum) in self.char_luminosities.iter().enumerate() {
+                let edge_sim = sims[[row, col]];
+                let lum_sim = 1.0 - (chunk_lums[col] - lum).abs(); // Range [0, 1], higher = more similar
+                let score = self.edge_weight * edge_sim + (1.0 - self.edge_weight) * lum_sim;
 
-            if score > best_score {
-                best_score = score;
-                best_idx = i;
+                if score > best_score {
+                    best_score = score;
+                    best_idx = row;
+                }
             }
+
+            results.push(self.chars[best_idx]);
         }
 
-        Ok(self.chars[best_idx])
+        Ok(results)
     }
 }
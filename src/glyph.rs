@@ -0,0 +1,55 @@
+//! Shared 8×16 glyph rasterization, used by both the DCT matcher and the
+//! PNG preview renderer so there's one definition of "what a char looks like".
+
+use crate::{PicunicError, Result};
+use fontdue::{Font, FontSettings};
+use std::path::Path;
+
+pub(crate) const CHUNK_W: usize = 8;
+pub(crate) const CHUNK_H: usize = 16;
+
+/// Loads a font once and rasterizes chars into 8×16 grayscale chunks on demand.
+pub struct GlyphRenderer {
+    font: Font,
+}
+
+impl GlyphRenderer {
+    pub fn new(font_path: impl AsRef<Path>) -> Result<Self> {
+        let font_data = std::fs::read(font_path)?;
+        let font = Font::from_bytes(font_data, FontSettings::default())
+            .map_err(|e| PicunicError::Model(e.to_string()))?;
+        Ok(Self { font })
+    }
+
+    /// Render `ch` into an 8×16 grayscale chunk (0.0-1.0).
+    pub(crate) fn render(&self, ch: char) -> Vec<f32> {
+        render_glyph(&self.font, ch)
+    }
+}
+
+/// Render `ch` into an 8×16 grayscale chunk (0.0-1.0), baseline at ~75%
+/// down, matching the rendering convention used elsewhere (`bigfoont`).
+pub(crate) fn render_glyph(font: &Font, ch: char) -> Vec<f32> {
+    let font_size = CHUNK_H as f32 * 0.875;
+    let (metrics, bitmap) = font.rasterize(ch, font_size);
+
+    let mut chunk = vec![0.0f32; CHUNK_W * CHUNK_H];
+    if metrics.width == 0 || metrics.height == 0 {
+        return chunk;
+    }
+
+    let baseline_y = (CHUNK_H as f32 * 0.75) as i32;
+    let y_offset = baseline_y - metrics.height as i32 - metrics.ymin;
+    let x_offset = (CHUNK_W as i32 - metrics.width as i32) / 2;
+
+    for sy in 0..metrics.height {
+        for sx in 0..metrics.width {
+            let tx = x_offset + sx as i32;
+            let ty = y_offset + sy as i32;
+            if tx >= 0 && tx < CHUNK_W as i32 && ty >= 0 && ty < CHUNK_H as i32 {
+                chunk[ty as usize * CHUNK_W + tx as usize] = bitmap[sy * metrics.width + sx] as f32 / 255.0;
+            }
+        }
+    }
+    chunk
+}
@@ -1,11 +1,18 @@
 //! Image to Unicode converter using CNN embeddings.
 
 pub mod chunk;
+pub mod color;
+pub mod container;
+pub mod dct;
 pub mod dither;
 pub mod embedding;
+pub mod glyph;
 
 pub use chunk::ImageChunker;
+pub use color::{Color, ColorMode, ColorSampler};
+pub use dct::DctMatcher;
 pub use embedding::EmbeddingMatcher;
+pub use glyph::GlyphRenderer;
 
 use thiserror::Error;
 
@@ -21,21 +28,70 @@ pub enum PicunicError {
 
 pub type Result<T> = std::result::Result<T, PicunicError>;
 
+/// Anything that can turn a grayscale chunk into a matched character.
+/// Implemented by `EmbeddingMatcher` (CNN embeddings) and `DctMatcher`
+/// (DCT descriptors, no model file required), so `Converter` can use
+/// either interchangeably.
+pub trait Matcher {
+    /// Match a single chunk (e.g. the `bigfoont` use case).
+    fn find_best_match(&mut self, chunk: &[f32]) -> Result<char>;
+
+    /// Match a whole image's worth of chunks at once. The default loops
+    /// `find_best_match`; matchers that can batch internally (like
+    /// `EmbeddingMatcher`'s single ONNX call) should override this.
+    fn find_best_matches(&mut self, chunks: &[Vec<f32>], _chunk_lums: &[f32]) -> Result<Vec<char>> {
+        chunks.iter().map(|c| self.find_best_match(c)).collect()
+    }
+
+    /// Restrict matching to ASCII output characters.
+    fn filter_ascii(&mut self);
+
+    /// Set the weight for edge similarity vs luminosity matching (0.0-1.0).
+    /// Matchers with no luminosity blend (like `DctMatcher`) ignore this.
+    fn set_edge_weight(&mut self, _weight: f32) {}
+}
+
+impl Matcher for EmbeddingMatcher {
+    fn find_best_match(&mut self, chunk: &[f32]) -> Result<char> {
+        self.find_best_match(chunk)
+    }
+
+    fn find_best_matches(&mut self, chunks: &[Vec<f32>], chunk_lums: &[f32]) -> Result<Vec<char>> {
+        self.find_best_matches(chunks, chunk_lums)
+    }
+
+    fn filter_ascii(&mut self) {
+        self.filter_ascii()
+    }
+
+    fn set_edge_weight(&mut self, weight: f32) {
+        self.set_edge_weight(weight)
+    }
+}
+
 /// Main converter using CNN embeddings
 pub struct Converter {
     width: u32,
-    matcher: EmbeddingMatcher,
+    matcher: Box<dyn Matcher>,
     dither: bool,
+    color_mode: Option<ColorMode>,
+    half_block: bool,
 }
 
 impl Converter {
     pub fn new(
         model_path: impl AsRef<std::path::Path>,
-        embeddings_path: impl AsRef<std::path::Path>,
-        chars_path: impl AsRef<std::path::Path>,
+        picu_path: impl AsRef<std::path::Path>,
     ) -> Result<Self> {
-        let matcher = EmbeddingMatcher::new(model_path, embeddings_path, chars_path)?;
-        Ok(Self { width: 80, matcher, dither: false })
+        let matcher = EmbeddingMatcher::new(model_path, picu_path)?;
+        Ok(Self { width: 80, matcher: Box::new(matcher), dither: false, color_mode: None, half_block: false })
+    }
+
+    /// Model-free alternative to `new`: matches glyphs by DCT descriptor
+    /// instead of CNN embeddings, needing only a font and a `chars` list.
+    pub fn new_dct(font_path: impl AsRef<std::path::Path>, chars: Vec<char>) -> Result<Self> {
+        let matcher = DctMatcher::new(font_path, chars)?;
+        Ok(Self { width: 80, matcher: Box::new(matcher), dither: false, color_mode: None, half_block: false })
     }
 
     pub fn with_width(mut self, width: u32) -> Self {
@@ -48,12 +104,66 @@ impl Converter {
         self
     }
 
+    /// Set the weight for edge similarity vs luminosity matching (0.0-1.0).
+    /// 1.0 is pure edge matching (the default), 0.0 is pure luminosity.
+    /// Forwarded to the underlying matcher; has no effect on `DctMatcher`.
+    pub fn with_edge_weight(mut self, weight: f32) -> Self {
+        self.matcher.set_edge_weight(weight);
+        self
+    }
+
+    /// Wrap each chosen glyph in an ANSI escape carrying that cell's mean
+    /// source color, in the given `ColorMode`. `None` disables color.
+    pub fn with_color(mut self, mode: Option<ColorMode>) -> Self {
+        self.color_mode = mode;
+        self
+    }
+
+    /// Skip glyph matching and render `▀` half-blocks with independent
+    /// foreground/background colors, doubling effective vertical color
+    /// resolution. Uses `with_color`'s mode, defaulting to truecolor.
+    pub fn with_half_block(mut self, enabled: bool) -> Self {
+        self.half_block = enabled;
+        self
+    }
+
     pub fn ascii_only(mut self) -> Self {
         self.matcher.filter_ascii();
         self
     }
 
     pub fn convert(&mut self, image: &image::DynamicImage) -> String {
+        if self.half_block {
+            return self.convert_half_block(image);
+        }
+
+        let (out_w, out_h, matched, colors) = self.match_grid(image);
+
+        let mut rows = Vec::with_capacity(out_h as usize);
+        for y in 0..out_h {
+            let row: String = (0..out_w)
+                .map(|x| {
+                    let idx = (y * out_w + x) as usize;
+                    let ch = matched.get(idx).copied().unwrap_or(' ');
+                    match &colors {
+                        Some(colors) => {
+                            let c = colors[idx];
+                            format!("{}{}{}", c.ansi_fg(self.color_mode.unwrap()), ch, color::ANSI_RESET)
+                        }
+                        None => ch.to_string(),
+                    }
+                })
+                .collect();
+            rows.push(row);
+        }
+
+        rows.join("\n") + "\n"
+    }
+
+    /// Chunk, match and (if color is enabled) color-sample `image` against
+    /// the current glyph grid, shared by `convert` and `convert_to_image`.
+    /// Returns `(out_w, out_h, matched chars, per-cell colors)`.
+    fn match_grid(&mut self, image: &image::DynamicImage) -> (u32, u32, Vec<char>, Option<Vec<Color>>) {
         let gray = image.to_luma8();
         let (img_w, img_h) = (gray.width(), gray.height());
 
@@ -64,7 +174,7 @@ impl Converter {
 
         // Apply dithering if enabled
         // Scale = pixels per character (character-sized features)
-        let gray = if self.dither {
+        let gray_for_chunks = if self.dither {
             let pixels_per_char = img_w / out_w;
             let scale = pixels_per_char.max(1);
             dither::dither_atkinson(&gray, scale)
@@ -72,16 +182,106 @@ impl Converter {
             gray
         };
 
-        let chunker = ImageChunker::new(gray, out_w, out_h);
+        let chunker = ImageChunker::new(gray_for_chunks, out_w, out_h);
+        let color_sampler = self.color_mode.map(|_| ColorSampler::new(image.to_rgba8(), out_w, out_h));
+
+        // Collect every chunk up front so matching can run as one batched
+        // inference + similarity GEMM instead of one per chunk.
+        let mut chunks = Vec::with_capacity((out_w * out_h) as usize);
+        let mut chunk_lums = Vec::with_capacity((out_w * out_h) as usize);
+        let mut colors = color_sampler.as_ref().map(|_| Vec::with_capacity((out_w * out_h) as usize));
+        for y in 0..out_h {
+            for x in 0..out_w {
+                let chunk = chunker.get_chunk(x, y);
+                let lum: f32 = chunk.iter().sum::<f32>() / chunk.len() as f32;
+                chunks.push(chunk);
+                chunk_lums.push(lum);
+                if let (Some(sampler), Some(colors)) = (&color_sampler, &mut colors) {
+                    colors.push(sampler.get_color(x, y));
+                }
+            }
+        }
+
+        let matched = self.matcher.find_best_matches(&chunks, &chunk_lums).unwrap_or_default();
+        (out_w, out_h, matched, colors)
+    }
+
+    /// Color-only render: two vertically stacked color samples per cell,
+    /// drawn as a `▀` glyph whose foreground is the top half and background
+    /// the bottom half.
+    fn convert_half_block(&mut self, image: &image::DynamicImage) -> String {
+        let rgba = image.to_rgba8();
+        let (img_w, img_h) = (rgba.width(), rgba.height());
+
+        let out_w = self.width;
+        let aspect = img_w as f32 / img_h as f32;
+        let out_h = (out_w as f32 / aspect * 0.5).round().max(1.0) as u32;
+
+        let sampler = ColorSampler::new(rgba, out_w, out_h);
+        let mode = self.color_mode.unwrap_or(ColorMode::Truecolor);
 
         let mut rows = Vec::with_capacity(out_h as usize);
         for y in 0..out_h {
             let row: String = (0..out_w)
-                .map(|x| self.matcher.find_best_match(&chunker.get_chunk(x, y)).unwrap_or(' '))
+                .map(|x| {
+                    let top = sampler.get_color_band(x, y, 2, 0);
+                    let bottom = sampler.get_color_band(x, y, 2, 1);
+                    format!("{}{}\u{2580}{}", top.ansi_fg(mode), bottom.ansi_bg(mode), color::ANSI_RESET)
+                })
                 .collect();
             rows.push(row);
         }
 
         rows.join("\n") + "\n"
     }
+
+    /// Rasterize the converted art back to an image: one matched glyph's
+    /// 8×16 bitmap per cell (rendered fresh via `font_path`, independent of
+    /// whatever the matcher used internally), tinted by that cell's color
+    /// when color mode is enabled.
+    pub fn convert_to_image(
+        &mut self,
+        image: &image::DynamicImage,
+        font_path: impl AsRef<std::path::Path>,
+    ) -> Result<image::RgbaImage> {
+        let renderer = GlyphRenderer::new(font_path)?;
+        let (out_w, out_h, matched, colors) = self.match_grid(image);
+
+        let mut canvas =
+            image::RgbaImage::new(out_w * glyph::CHUNK_W as u32, out_h * glyph::CHUNK_H as u32);
+        for y in 0..out_h {
+            for x in 0..out_w {
+                let idx = (y * out_w + x) as usize;
+                let ch = matched.get(idx).copied().unwrap_or(' ');
+                let fg = colors.as_ref().map(|c| c[idx]).unwrap_or(Color { r: 255, g: 255, b: 255, a: 255 });
+                let bitmap = renderer.render(ch);
+
+                for ty in 0..glyph::CHUNK_H {
+                    for tx in 0..glyph::CHUNK_W {
+                        let intensity = bitmap[ty * glyph::CHUNK_W + tx];
+                        let px = image::Rgba([
+                            (fg.r as f32 * intensity).round() as u8,
+                            (fg.g as f32 * intensity).round() as u8,
+                            (fg.b as f32 * intensity).round() as u8,
+                            255,
+                        ]);
+                        canvas.put_pixel(x * glyph::CHUNK_W as u32 + tx as u32, y * glyph::CHUNK_H as u32 + ty as u32, px);
+                    }
+                }
+            }
+        }
+
+        Ok(canvas)
+    }
+
+    /// Convenience wrapper around `convert_to_image` that encodes straight to a PNG file.
+    pub fn save_png(
+        &mut self,
+        image: &image::DynamicImage,
+        font_path: impl AsRef<std::path::Path>,
+        out_path: impl AsRef<std::path::Path>,
+    ) -> Result<()> {
+        self.convert_to_image(image, font_path)?.save(out_path)?;
+        Ok(())
+    }
 }
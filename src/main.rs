@@ -1,7 +1,7 @@
 //! picunic CLI - Convert images to Unicode art using CNN embeddings
 
 use clap::Parser;
-use picunic::{Converter, PicunicError};
+use picunic::{ColorMode, Converter, PicunicError};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -27,19 +27,23 @@ struct Args {
     /// Edge weight for edge vs luminosity matching (0.0-1.0, default: 1.0 = pure edge)
     #[arg(long, default_value = "1.0")]
     edge_weight: f32,
+    /// Colorize output with the source image's per-cell average color
+    #[arg(short, long, value_enum)]
+    color: Option<ColorMode>,
+    /// Render `▀` half-block color cells instead of matched glyphs (implies --color)
+    #[arg(long)]
+    half_block: bool,
 }
 
 fn main() -> Result<(), PicunicError> {
     let args = Args::parse();
 
-    let mut converter = Converter::new(
-        args.model_dir.join("encoder.onnx"),
-        args.model_dir.join("encoder.embeddings.bin"),
-        args.model_dir.join("encoder.chars.json"),
-    )?
-    .with_width(args.width)
-    .with_dither(args.dither)
-    .with_edge_weight(args.edge_weight);
+    let mut converter = Converter::new(args.model_dir.join("encoder.onnx"), args.model_dir.join("encoder.picu"))?
+        .with_width(args.width)
+        .with_dither(args.dither)
+        .with_edge_weight(args.edge_weight)
+        .with_color(args.color)
+        .with_half_block(args.half_block);
 
     if args.ascii {
         converter = converter.ascii_only();
@@ -3,58 +3,45 @@
 use wasm_bindgen::prelude::*;
 use image::DynamicImage;
 use crate::chunk;
+use crate::color::ColorSampler;
+use crate::container::{self, Embeddings};
 use crate::dither;
 
 #[wasm_bindgen]
 pub struct WasmConverter {
     width: u32,
-    char_embeddings: Vec<f32>,
+    char_embeddings: Embeddings,
     chars: Vec<char>,
     char_luminosities: Vec<f32>,  // Precomputed average luminosities (0-1 range)
     embedding_dim: usize,
     dither: bool,
     ascii_only: bool,
     edge_weight: f32,  // Weight for edge similarity (0-1), luminosity weight is (1 - edge_weight)
+    color: bool,
 }
 
 #[wasm_bindgen]
 impl WasmConverter {
-    /// Create a new converter with pre-loaded embeddings and luminosities
-    /// 
+    /// Create a new converter from a `.picu` model container (a single
+    /// fetch on the JS side instead of three loose JSON/binary assets),
+    /// validating its CRC-32 before use.
+    ///
     /// # Arguments
-    /// * `char_embeddings` - Flat array of embeddings: [char0_emb[0..dim], char1_emb[0..dim], ...]
-    /// * `chars` - Array of characters corresponding to embeddings
-    /// * `embedding_dim` - Dimension of each embedding vector
-    /// * `char_luminosities` - Array of average luminosities (0-1) for each character
+    /// * `picu_bytes` - Contents of a `.picu` file
     #[wasm_bindgen(constructor)]
-    pub fn new(
-        char_embeddings: Vec<f32>,
-        chars: Vec<String>,
-        embedding_dim: usize,
-        char_luminosities: Vec<f32>,
-    ) -> Result<WasmConverter, JsValue> {
-        let chars: Vec<char> = chars
-            .into_iter()
-            .filter_map(|s| s.chars().next())
-            .collect();
-        
-        if char_embeddings.len() != chars.len() * embedding_dim {
-            return Err(JsValue::from_str("Embeddings length doesn't match chars * dim"));
-        }
-
-        if char_luminosities.len() != chars.len() {
-            return Err(JsValue::from_str("Luminosities length doesn't match chars length"));
-        }
+    pub fn new(picu_bytes: &[u8]) -> Result<WasmConverter, JsValue> {
+        let model = container::read_picu(picu_bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         Ok(WasmConverter {
             width: 80,
-            char_embeddings,
-            chars,
-            char_luminosities,
-            embedding_dim,
+            char_embeddings: model.embeddings,
+            chars: model.chars,
+            char_luminosities: model.luminosities,
+            embedding_dim: model.embedding_dim,
             dither: false,
             ascii_only: false,
-            edge_weight: 1.0,  // Default: pure edge matching
+            edge_weight: 1.0, // Default: pure edge matching
+            color: false,
         })
     }
 
@@ -73,6 +60,12 @@ impl WasmConverter {
         self.ascii_only = enabled;
     }
 
+    /// Enable returning a parallel per-chunk RGB color array from `process_image`.
+    #[wasm_bindgen]
+    pub fn set_color(&mut self, enabled: bool) {
+        self.color = enabled;
+    }
+
     /// Set the weight for edge similarity vs luminosity matching (0.0-1.0)
     /// - 1.0: pure edge matching (default)
     /// - 0.0: pure luminosity matching
@@ -83,17 +76,18 @@ impl WasmConverter {
     }
 
     /// Process image and return chunk data for each position
-    /// Returns a flat array: [chunk0_data..., chunk1_data..., ...] where each chunk is 128 floats (8x16)
+    /// Returns a flat array: [chunk0_data..., chunk1_data..., ...] where each chunk is 128 floats (8x16).
+    /// When color mode is enabled, also returns a `colors` array of 3 bytes (r, g, b) per chunk.
     #[wasm_bindgen]
     pub fn process_image(&self, image_data: &[u8], width: u32, height: u32) -> Result<js_sys::Object, JsValue> {
         // Convert RGBA to DynamicImage
         let img = image::RgbaImage::from_raw(width, height, image_data.to_vec())
             .ok_or_else(|| JsValue::from_str("Invalid image dimensions"))?;
         let dynamic_img = DynamicImage::ImageRgba8(img);
-        
+
         // Convert to grayscale
         let mut gray = dynamic_img.to_luma8();
-        
+
         // Apply dithering if enabled
         if self.dither {
             let img_w = gray.width();
@@ -110,20 +104,27 @@ impl WasmConverter {
 
         // Create chunker
         let chunker = chunk::ImageChunker::new(gray, out_w, out_h);
+        let color_sampler =
+            self.color.then(|| ColorSampler::new(dynamic_img.to_rgba8(), out_w, out_h));
 
-        // Extract all chunks and compute luminosities
+        // Extract all chunks, luminosities, and (optionally) colors
         let mut chunks = Vec::new();
         let mut luminosities = Vec::new();
+        let mut colors = Vec::new();
         for y in 0..out_h {
             for x in 0..out_w {
                 let chunk = chunker.get_chunk(x, y);
                 let lum: f32 = chunk.iter().sum::<f32>() / chunk.len() as f32;
                 chunks.push(chunk);
                 luminosities.push(lum);
+                if let Some(sampler) = &color_sampler {
+                    let c = sampler.get_color(x, y);
+                    colors.extend_from_slice(&[c.r, c.g, c.b]);
+                }
             }
         }
 
-        // Return as object with chunks, luminosities, and dimensions
+        // Return as object with chunks, luminosities, colors, and dimensions
         let result = js_sys::Object::new();
         let chunks_array = js_sys::Array::new();
         for chunk in chunks {
@@ -134,6 +135,10 @@ impl WasmConverter {
         js_sys::Reflect::set(&result, &"luminosities".into(), &lum_array)?;
         js_sys::Reflect::set(&result, &"width".into(), &(out_w as u32).into())?;
         js_sys::Reflect::set(&result, &"height".into(), &(out_h as u32).into())?;
+        if self.color {
+            let colors_array = js_sys::Uint8Array::from(&colors[..]);
+            js_sys::Reflect::set(&result, &"colors".into(), &colors_array)?;
+        }
 
         Ok(result)
     }
@@ -150,11 +155,31 @@ impl WasmConverter {
             return Err(JsValue::from_str("Embedding dimension mismatch"));
         }
 
-        // Cosine similarity (assuming embeddings are normalized, range [-1, 1])
+        // Cosine similarity (assuming embeddings are normalized, range [-1, 1]),
+        // as a float dot product or, for a quantized table, an integer one.
+        let edge_sims: Vec<f32> = match &self.char_embeddings {
+            Embeddings::F32(data) => data
+                .chunks_exact(self.embedding_dim)
+                .map(|char_emb| {
+                    let sim: f32 = embedding.iter().zip(char_emb.iter()).map(|(a, b)| a * b).sum();
+                    (sim + 1.0) * 0.5
+                })
+                .collect(),
+            Embeddings::I8 { data, scale } => {
+                let (query, query_scale) = container::quantize(embedding);
+                data.chunks_exact(self.embedding_dim)
+                    .map(|char_emb| {
+                        let dot: i32 = char_emb.iter().zip(query.iter()).map(|(&a, &b)| a as i32 * b as i32).sum();
+                        (dot as f32 / (scale * query_scale) + 1.0) * 0.5
+                    })
+                    .collect()
+            }
+        };
+
         let mut best_idx = 0;
         let mut best_score = f32::NEG_INFINITY;
 
-        for (i, char_emb) in self.char_embeddings.chunks_exact(self.embedding_dim).enumerate() {
+        for (i, &normalized_edge_sim) in edge_sims.iter().enumerate() {
             if self.ascii_only {
                 let c = self.chars[i];
                 if (c as u32) < 0x20 || (c as u32) > 0x7E {
@@ -162,13 +187,6 @@ impl WasmConverter {
                 }
             }
 
-            // Edge similarity (cosine similarity, normalized to [0, 1])
-            let edge_sim: f32 = embedding.iter()
-                .zip(char_emb.iter())
-                .map(|(a, b)| a * b)
-                .sum();
-            let normalized_edge_sim = (edge_sim + 1.0) * 0.5;
-
             // Luminosity similarity: 1 - normalized absolute difference
             let lum_diff = (chunk_lum - self.char_luminosities[i]).abs();
             let lum_sim = 1.0 - lum_diff;  // Range [0, 1], higher = more similar